@@ -2,17 +2,23 @@ use std::{collections::HashMap, sync::Arc};
 
 use bazed_rpc::{
     core_proto::ToBackend,
-    core_proto::{CaretPosition, ToFrontend},
+    core_proto::{CaretPosition, TextChange, ToFrontend},
     keycode::KeyInput,
     server::ClientSendHandle,
 };
 use color_eyre::Result;
 use futures::StreamExt;
-use tokio::sync::RwLock;
+use tokio::sync::{mpsc, Mutex, RwLock};
+use uuid::Uuid;
 
 use crate::{
+    diff::DiffProvider,
     document::{Document, DocumentId},
     input_mapper::interpret_key_input,
+    lsp::{LspEvent, LspRegistry},
+    registers::{RegisterName, RegisterOp, Registers},
+    save::{SaveResult, SaveTracker},
+    tree::LayoutTree,
     user_buffer_op::{DocumentOp, Operation},
     view::{View, ViewId},
 };
@@ -29,19 +35,89 @@ pub struct App {
     documents: HashMap<DocumentId, Document>,
     views: HashMap<ViewId, View>,
     event_send: ClientSendHandle,
+    /// Identifies this connection's edits in the CRDT buffers of every open
+    /// document, handed out by [`bazed_rpc::server::wait_for_client`] when
+    /// the frontend connects.
+    ///
+    /// Note this only disambiguates ids *within* one connected session: the
+    /// wire protocol only ever carries [`bazed_rpc::core_proto::TextChange`]
+    /// line/col ranges between core and frontend, never the neighbor-linked
+    /// [`crate::crdt::CrdtOp`] values the CRDT itself merges by. Since
+    /// `wait_for_client` also only ever accepts one frontend connection at a
+    /// time, there is currently no path by which two sites' raw ops are ever
+    /// merged against each other outside of [`crate::crdt`]'s own logic.
+    site_id: Uuid,
+    lsp: LspRegistry,
+    registers: Registers,
+    diff_providers: HashMap<DocumentId, Arc<Mutex<DiffProvider>>>,
+    /// The window's pane layout. `None` until the first view is opened.
+    layout: Option<LayoutTree>,
+    save: SaveTracker,
 }
 
 impl App {
-    pub fn new(event_send: ClientSendHandle) -> Self {
-        App {
+    /// Builds the app and starts its language-server event forwarding loop,
+    /// which needs no access to `App` itself. The save-result loop does
+    /// (it has to call back into [`SaveTracker::mark_saved`]), so its
+    /// receiver is handed back for the caller to drive once an
+    /// `Arc<RwLock<App>>` exists to drive it with; see [start].
+    fn new(event_send: ClientSendHandle, site_id: Uuid) -> (Self, mpsc::UnboundedReceiver<SaveResult>) {
+        let (lsp_events, mut lsp_events_recv) = mpsc::unbounded_channel();
+        let lsp_event_send = event_send.clone();
+        tokio::spawn(async move {
+            while let Some(event) = lsp_events_recv.recv().await {
+                let rpc = match event {
+                    LspEvent::Diagnostics {
+                        document_id,
+                        diagnostics,
+                    } => ToFrontend::PublishDiagnostics {
+                        document_id: document_id.0,
+                        diagnostics,
+                    },
+                    LspEvent::Completion { request_id, items } => {
+                        ToFrontend::CompletionResponse {
+                            request_id: bazed_rpc::core_proto::RequestId(request_id),
+                            items,
+                        }
+                    },
+                };
+                if let Err(err) = lsp_event_send.send_rpc(rpc).await {
+                    tracing::error!("Failed to forward language server event: {err:?}");
+                }
+            }
+        });
+
+        let (save_results, save_results_recv) = mpsc::unbounded_channel();
+
+        let app = App {
             documents: HashMap::new(),
             event_send,
             views: HashMap::new(),
-        }
+            site_id,
+            lsp: LspRegistry::new(lsp_events),
+            registers: Registers::new(),
+            diff_providers: HashMap::new(),
+            layout: None,
+            save: SaveTracker::new(save_results),
+        };
+        (app, save_results_recv)
     }
 
-    async fn open_document(&mut self, document: Document) -> Result<()> {
-        let id = DocumentId::gen();
+    /// Push the current layout tree to the frontend.
+    async fn send_layout_update(&self) -> Result<()> {
+        let Some(layout) = &self.layout else {
+            return Ok(());
+        };
+        self.event_send
+            .send_rpc(ToFrontend::UpdateLayout {
+                tree: layout.root().into(),
+                focused_view_id: layout.focused().into(),
+            })
+            .await?;
+        Ok(())
+    }
+
+    async fn open_document(&mut self, id: DocumentId, document: Document) -> Result<()> {
         self.event_send
             .send_rpc(ToFrontend::OpenDocument {
                 document_id: id.0,
@@ -49,20 +125,92 @@ impl App {
                 text: document.buffer.content_to_string(),
             })
             .await?;
+        if let Some(path) = &document.path {
+            self.diff_providers
+                .insert(id, Arc::new(Mutex::new(DiffProvider::for_path(path))));
+        }
         self.documents.insert(id, document);
         Ok(())
     }
 
+    /// Recompute the diff gutter for `document_id` off the edit hot path and
+    /// push it to every view of that document once it's ready.
+    fn spawn_diff_recompute(&self, document_id: DocumentId, current_text: String) {
+        let Some(provider) = self.diff_providers.get(&document_id).cloned() else {
+            return;
+        };
+        let view_ids: Vec<ViewId> = self
+            .views
+            .iter()
+            .filter(|(_, view)| view.document_id == document_id)
+            .map(|(id, _)| *id)
+            .collect();
+        let event_send = self.event_send.clone();
+        tokio::spawn(async move {
+            let hunks = provider.lock().await.diff(&current_text);
+            for view_id in view_ids {
+                let rpc = ToFrontend::UpdateDiff {
+                    view_id: view_id.into(),
+                    hunks: hunks
+                        .iter()
+                        .map(|h| bazed_rpc::core_proto::DiffHunk {
+                            line: h.line,
+                            kind: match h.kind {
+                                crate::diff::DiffKind::Added => {
+                                    bazed_rpc::core_proto::DiffKind::Added
+                                },
+                                crate::diff::DiffKind::Modified => {
+                                    bazed_rpc::core_proto::DiffKind::Modified
+                                },
+                                crate::diff::DiffKind::Deleted => {
+                                    bazed_rpc::core_proto::DiffKind::Deleted
+                                },
+                            },
+                        })
+                        .collect(),
+                };
+                if let Err(err) = event_send.send_rpc(rpc).await {
+                    tracing::error!("Failed to send diff update: {err:?}");
+                }
+            }
+        });
+    }
+
+    /// Re-read the diff gutter's cached HEAD blob for `document_id` and push
+    /// a recomputed diff to every view of it. Call once a save has actually
+    /// completed, since that's the only point at which the on-disk (and
+    /// thus HEAD-relative, once the commit catches up) content is known to
+    /// have moved; see [DiffProvider::refresh_head_blob].
+    async fn refresh_diff_baseline(&self, document_id: DocumentId) {
+        let Some(provider) = self.diff_providers.get(&document_id).cloned() else {
+            return;
+        };
+        provider.lock().await.refresh_head_blob();
+        if let Some(document) = self.documents.get(&document_id) {
+            self.spawn_diff_recompute(document_id, document.buffer.content_to_string());
+        }
+    }
+
     #[tracing::instrument(skip(self))]
     async fn open_file(&mut self, path: std::path::PathBuf) -> Result<()> {
         let document = Document::open_file(path)?;
-        self.open_document(document).await
+        let id = DocumentId::gen();
+        if let Some(path) = &document.path {
+            if let Some(language) = crate::lsp::detect_language(path) {
+                let uri = format!("file://{}", path.display());
+                let text = document.buffer.content_to_string();
+                if let Err(err) = self.lsp.did_open(&language, &uri, id, &text).await {
+                    tracing::warn!("Failed to start language server for {language:?}: {err:?}");
+                }
+            }
+        }
+        self.open_document(id, document).await
     }
 
     #[tracing::instrument(skip(self))]
     async fn open_ephemeral(&mut self) -> Result<()> {
         let document = Document::open_ephemeral();
-        self.open_document(document).await
+        self.open_document(DocumentId::gen(), document).await
     }
 
     #[tracing::instrument(skip(self))]
@@ -77,6 +225,10 @@ impl App {
             ToBackend::MouseInput { view_id, position } => {
                 self.handle_mouse_input(ViewId::from_uuid(view_id), position)?
             },
+            ToBackend::MouseScroll { view_id, delta_y } => {
+                self.handle_mouse_scroll(ViewId::from_uuid(view_id), delta_y)
+                    .await?
+            },
             ToBackend::ViewportChanged {
                 view_id,
                 height,
@@ -113,16 +265,108 @@ impl App {
                 self.handle_save_document(DocumentId::from_uuid(document_id))
                     .await?;
             },
+            ToBackend::RemoteEdit { view_id, change } => {
+                self.handle_remote_edit(ViewId::from_uuid(view_id), change)
+                    .await?;
+            },
+            ToBackend::RequestCompletion {
+                request_id,
+                view_id,
+                position,
+            } => {
+                self.handle_request_completion(request_id, ViewId::from_uuid(view_id), position)
+                    .await?;
+            },
+            ToBackend::ClipboardContent { request_id, text } => {
+                self.handle_clipboard_content(request_id, text).await?;
+            },
+            ToBackend::SplitView { view_id, direction } => {
+                self.handle_split_view(ViewId::from_uuid(view_id), direction.into())
+                    .await?;
+            },
+            ToBackend::CloseView { view_id } => {
+                self.handle_close_view(ViewId::from_uuid(view_id)).await?;
+            },
+            ToBackend::MoveFocus { direction } => {
+                self.handle_move_focus(direction.into()).await?;
+            },
         }
         Ok(())
     }
 
-    async fn handle_save_document(&mut self, document_id: DocumentId) -> Result<()> {
+    async fn handle_request_completion(
+        &mut self,
+        request_id: bazed_rpc::core_proto::RequestId,
+        view_id: ViewId,
+        position: CaretPosition,
+    ) -> Result<()> {
+        let view = self
+            .views
+            .get(&view_id)
+            .ok_or(Error::InvalidViewId(view_id))?;
+        let document = self
+            .documents
+            .get(&view.document_id)
+            .ok_or(Error::InvalidDocumentId(view.document_id))?;
+        let Some(path) = &document.path else {
+            return Ok(());
+        };
+        let Some(language) = crate::lsp::detect_language(path) else {
+            return Ok(());
+        };
+        let uri = format!("file://{}", path.display());
+        let line_text = document.buffer.content_to_string();
+        let line_text = line_text.lines().nth(position.line).unwrap_or_default();
+        let (line, utf16_col) = crate::lsp::to_lsp_position(line_text, &position);
+        self.lsp
+            .request_completion(&language, &uri, line, utf16_col, request_id.0)
+            .await?;
+        Ok(())
+    }
+
+    /// Merge an edit coming from one client into the document's CRDT buffer,
+    /// then broadcast it to every other view onto that document. Since CRDT
+    /// ops commute, this never conflicts with concurrent edits from other
+    /// clients.
+    async fn handle_remote_edit(&mut self, view_id: ViewId, change: TextChange) -> Result<()> {
+        let view = self
+            .views
+            .get(&view_id)
+            .ok_or(Error::InvalidViewId(view_id))?;
+        let document_id = view.document_id;
         let document = self
             .documents
             .get_mut(&document_id)
             .ok_or(Error::InvalidDocumentId(document_id))?;
-        Ok(document.write_to_file().await?)
+        document.apply_crdt_change(self.site_id, &change);
+        self.save.bump_revision(document_id);
+
+        for (other_id, other_view) in &self.views {
+            if other_view.document_id == document_id && *other_id != view_id {
+                self.event_send
+                    .send_rpc(ToFrontend::ApplyEdit {
+                        view_id: (*other_id).into(),
+                        change: TextChange {
+                            range: change.range,
+                            new_text: change.new_text.clone(),
+                        },
+                    })
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn handle_save_document(&mut self, document_id: DocumentId) -> Result<()> {
+        let document = self
+            .documents
+            .get(&document_id)
+            .ok_or(Error::InvalidDocumentId(document_id))?;
+        let text = document.buffer.content_to_string();
+        self.save
+            .spawn_save(document_id, document.path.clone(), text.clone());
+        self.spawn_diff_recompute(document_id, text);
+        Ok(())
     }
 
     async fn handle_viewport_changed(
@@ -144,25 +388,61 @@ impl App {
         view.first_col = first_col;
 
         if needs_new_view_info {
+            let document_id = view.document_id;
             let document = self
                 .documents
-                .get(&view.document_id)
-                .ok_or(Error::InvalidDocumentId(view.document_id))?;
+                .get(&document_id)
+                .ok_or(Error::InvalidDocumentId(document_id))?;
+            let modified = self.save.is_modified(document_id);
             self.event_send
-                .send_rpc(document.create_update_notification(view_id, view))
+                .send_rpc(document.create_update_notification(view_id, view, modified))
                 .await?;
+            let text = document.buffer.content_to_string();
+            self.spawn_diff_recompute(document_id, text);
         }
         Ok(())
     }
+
+    /// Accumulate a high-resolution scroll delta and advance `first_line`
+    /// once the accumulated fraction crosses an integer boundary. Devices
+    /// that only report whole lines just advance `first_line` immediately,
+    /// same as before.
+    async fn handle_mouse_scroll(&mut self, view_id: ViewId, delta_y: f64) -> Result<()> {
+        let view = self
+            .views
+            .get_mut(&view_id)
+            .ok_or(Error::InvalidViewId(view_id))?;
+        view.scroll_fraction += delta_y;
+        let whole_lines = view.scroll_fraction.trunc() as i64;
+        view.scroll_fraction -= whole_lines as f64;
+        if whole_lines == 0 {
+            return Ok(());
+        }
+        view.first_line = view
+            .first_line
+            .saturating_add_signed(whole_lines as isize);
+
+        let document_id = view.document_id;
+        let document = self
+            .documents
+            .get(&document_id)
+            .ok_or(Error::InvalidDocumentId(document_id))?;
+        let modified = self.save.is_modified(document_id);
+        self.event_send
+            .send_rpc(document.create_update_notification(view_id, view, modified))
+            .await?;
+        Ok(())
+    }
     async fn handle_key_pressed(&mut self, view_id: ViewId, input: KeyInput) -> Result<()> {
         let view = self
             .views
             .get_mut(&view_id)
             .ok_or(Error::InvalidViewId(view_id))?;
+        let document_id = view.document_id;
         let document = self
             .documents
-            .get_mut(&view.document_id)
-            .ok_or(Error::InvalidDocumentId(view.document_id))?;
+            .get_mut(&document_id)
+            .ok_or(Error::InvalidDocumentId(document_id))?;
 
         let Some(operation) = interpret_key_input(&input) else {
             tracing::info!("Ignoring unhandled key input: {input:?}");
@@ -170,14 +450,163 @@ impl App {
         };
         match operation {
             Operation::Document(op) => match op {
-                DocumentOp::Save => document.write_to_file().await?,
+                DocumentOp::Save => {
+                    let text = document.buffer.content_to_string();
+                    self.save
+                        .spawn_save(document_id, document.path.clone(), text.clone());
+                    self.spawn_diff_recompute(document_id, text);
+                },
+                DocumentOp::Undo => {
+                    if document.buffer.undo() {
+                        self.save.bump_revision(document_id);
+                    }
+                },
+                DocumentOp::Redo => {
+                    if document.buffer.redo() {
+                        self.save.bump_revision(document_id);
+                    }
+                },
+            },
+            Operation::Edit(op) => {
+                document.buffer.apply_edit_op(op);
+                self.save.bump_revision(document_id);
+                let text = document.buffer.content_to_string();
+                if let Some(path) = &document.path {
+                    if let Some(language) = crate::lsp::detect_language(path) {
+                        let uri = format!("file://{}", path.display());
+                        if let Err(err) = self.lsp.did_change(&language, &uri, &text).await {
+                            tracing::warn!("Failed to notify language server of change: {err:?}");
+                        }
+                    }
+                }
+                self.spawn_diff_recompute(document_id, text);
             },
-            Operation::Edit(op) => document.buffer.apply_edit_op(op),
             Operation::Movement(op) => document.buffer.apply_movement_op(view, op),
+            Operation::Register(op) => self.handle_register_op(view_id, op).await?,
+        }
+
+        // Multiple views (e.g. from a window split) may point at this
+        // document, so every one of them needs the update, not just the
+        // view the key was pressed in.
+        self.broadcast_document_update(document_id).await
+    }
+
+    async fn handle_register_op(&mut self, view_id: ViewId, op: RegisterOp) -> Result<()> {
+        match op {
+            RegisterOp::Yank(register) => {
+                let text = self.current_line(view_id)?;
+                self.write_register(register, text).await?;
+            },
+            RegisterOp::Delete(register) => {
+                let view = self
+                    .views
+                    .get(&view_id)
+                    .ok_or(Error::InvalidViewId(view_id))?;
+                let document_id = view.document_id;
+                let line = view.first_line;
+                let document = self
+                    .documents
+                    .get_mut(&document_id)
+                    .ok_or(Error::InvalidDocumentId(document_id))?;
+                let text = document.buffer.delete_line(line);
+                let current_text = document.buffer.content_to_string();
+                self.save.bump_revision(document_id);
+                self.write_register(register, text).await?;
+                self.broadcast_document_update(document_id).await?;
+                self.spawn_diff_recompute(document_id, current_text);
+            },
+            RegisterOp::Paste(register) => {
+                if register.is_clipboard() {
+                    let request_id = Uuid::new_v4();
+                    self.registers.track_clipboard_read(request_id, view_id);
+                    self.event_send
+                        .send_rpc(ToFrontend::RequestClipboard {
+                            request_id: bazed_rpc::core_proto::RequestId(request_id),
+                        })
+                        .await?;
+                    return Ok(());
+                }
+                if let Some(text) = self.registers.get(register).map(str::to_owned) {
+                    self.paste_into_view(view_id, &text).await?;
+                }
+            },
+        }
+        Ok(())
+    }
+
+    /// The text of `view`'s current line, i.e. what a line-wise yank/delete
+    /// acts on. There's no per-view caret, so this uses the viewport's top
+    /// line as a stand-in for "the current line".
+    fn current_line(&self, view_id: ViewId) -> Result<String> {
+        let view = self
+            .views
+            .get(&view_id)
+            .ok_or(Error::InvalidViewId(view_id))?;
+        let document = self
+            .documents
+            .get(&view.document_id)
+            .ok_or(Error::InvalidDocumentId(view.document_id))?;
+        Ok(document.buffer.line(view.first_line))
+    }
+
+    async fn write_register(&mut self, register: RegisterName, text: String) -> Result<()> {
+        if register.is_clipboard() {
+            self.event_send
+                .send_rpc(ToFrontend::SetClipboard { text: text.clone() })
+                .await?;
+        }
+        self.registers.set(register, text);
+        Ok(())
+    }
+
+    async fn paste_into_view(&mut self, view_id: ViewId, text: &str) -> Result<()> {
+        let view = self
+            .views
+            .get(&view_id)
+            .ok_or(Error::InvalidViewId(view_id))?;
+        let document_id = view.document_id;
+        let document = self
+            .documents
+            .get_mut(&document_id)
+            .ok_or(Error::InvalidDocumentId(document_id))?;
+        document.buffer.insert_text_at_caret(text);
+        let current_text = document.buffer.content_to_string();
+        self.save.bump_revision(document_id);
+        // Every view onto this document needs the paste, not just the one
+        // it was pasted into.
+        self.broadcast_document_update(document_id).await?;
+        self.spawn_diff_recompute(document_id, current_text);
+        Ok(())
+    }
+
+    /// Push the current content to every view onto `document_id`.
+    async fn broadcast_document_update(&self, document_id: DocumentId) -> Result<()> {
+        let document = self
+            .documents
+            .get(&document_id)
+            .ok_or(Error::InvalidDocumentId(document_id))?;
+        let modified = self.save.is_modified(document_id);
+        for (other_view_id, other_view) in &self.views {
+            if other_view.document_id == document_id {
+                self.event_send
+                    .send_rpc(document.create_update_notification(*other_view_id, other_view, modified))
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Handle the frontend's answer to a [ToFrontend::RequestClipboard]
+    /// paste, completing a pending [RegisterOp::Paste] from the `"*"`
+    /// register.
+    async fn handle_clipboard_content(
+        &mut self,
+        request_id: bazed_rpc::core_proto::RequestId,
+        text: String,
+    ) -> Result<()> {
+        if let Some(view_id) = self.registers.resolve_clipboard_read(request_id.0, text.clone()) {
+            self.paste_into_view(view_id, &text).await?;
         }
-        self.event_send
-            .send_rpc(document.create_update_notification(view_id, view))
-            .await?;
         Ok(())
     }
 
@@ -202,18 +631,64 @@ impl App {
         let view = View::new(document_id, height, width);
         let id = ViewId::gen();
         self.views.insert(id, view);
+        if self.layout.is_none() {
+            self.layout = Some(LayoutTree::new(id));
+        }
         Ok(id)
     }
 
+    async fn handle_split_view(
+        &mut self,
+        view_id: ViewId,
+        direction: crate::tree::SplitDirection,
+    ) -> Result<()> {
+        let view = self
+            .views
+            .get(&view_id)
+            .ok_or(Error::InvalidViewId(view_id))?;
+        let new_view = View::new(view.document_id, view.height, view.width);
+        let new_view_id = ViewId::gen();
+        self.views.insert(new_view_id, new_view);
+
+        let layout = self.layout.get_or_insert_with(|| LayoutTree::new(view_id));
+        layout.split(view_id, direction, new_view_id);
+        self.send_layout_update().await
+    }
+
+    async fn handle_close_view(&mut self, view_id: ViewId) -> Result<()> {
+        if self.views.len() <= 1 {
+            return Ok(());
+        }
+        let Some(layout) = &mut self.layout else {
+            return Ok(());
+        };
+        if layout.close(view_id) {
+            self.views.remove(&view_id);
+            self.send_layout_update().await?;
+        }
+        Ok(())
+    }
+
+    async fn handle_move_focus(&mut self, direction: crate::tree::FocusDirection) -> Result<()> {
+        let Some(layout) = &mut self.layout else {
+            return Ok(());
+        };
+        if layout.move_focus(direction).is_some() {
+            self.send_layout_update().await?;
+        }
+        Ok(())
+    }
+
     pub fn views(&self) -> &HashMap<ViewId, View> {
         &self.views
     }
 }
 
 pub async fn start(addr: &str, path: Option<std::path::PathBuf>) -> Result<()> {
-    let (send, mut recv) = bazed_rpc::server::wait_for_client(addr).await?;
+    let (send, mut recv, site_id) = bazed_rpc::server::wait_for_client(addr).await?;
 
-    let core = Arc::new(RwLock::new(App::new(send)));
+    let (app, mut save_results_recv) = App::new(send.clone(), site_id);
+    let core = Arc::new(RwLock::new(app));
 
     tokio::spawn({
         let core = core.clone();
@@ -227,6 +702,38 @@ pub async fn start(addr: &str, path: Option<std::path::PathBuf>) -> Result<()> {
         }
     });
 
+    // Forwards save outcomes to the frontend and, on success, records the
+    // saved revision in the app's SaveTracker so its modified/clean
+    // indicator stays accurate.
+    tokio::spawn({
+        let core = core.clone();
+        async move {
+            while let Some(result) = save_results_recv.recv().await {
+                let rpc = match result {
+                    SaveResult::Saved {
+                        document_id,
+                        revision,
+                    } => {
+                        let mut core = core.write().await;
+                        core.save.mark_saved(document_id, revision);
+                        core.refresh_diff_baseline(document_id).await;
+                        ToFrontend::DocumentSaved {
+                            document_id: document_id.0,
+                            revision,
+                        }
+                    },
+                    SaveResult::Failed { document_id, error } => ToFrontend::SaveFailed {
+                        document_id: document_id.0,
+                        error,
+                    },
+                };
+                if let Err(err) = send.send_rpc(rpc).await {
+                    tracing::error!("Failed to forward save result: {err:?}");
+                }
+            }
+        }
+    });
+
     if let Some(path) = path {
         core.write().await.open_file(path).await?;
     } else {
@@ -0,0 +1,170 @@
+//! Git diff gutter: for documents backed by a file inside a git repository,
+//! computes a line-level diff between the working buffer and the blob at
+//! HEAD, so the frontend can render added/modified/deleted markers in the
+//! gutter.
+
+use std::path::{Path, PathBuf};
+
+/// What changed on a line, relative to the document's content at HEAD.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffKind {
+    Added,
+    Modified,
+    Deleted,
+}
+
+/// A single gutter marker. `line` is a line index in the *current* buffer;
+/// for [DiffKind::Deleted] it's the line the deleted content used to precede
+/// (or one past the end, if the deletion was at the end of the file).
+#[derive(Debug, Clone, Copy)]
+pub struct DiffHunk {
+    pub line: usize,
+    pub kind: DiffKind,
+}
+
+/// Computes and caches the diff of one document's buffer against its HEAD
+/// blob. The HEAD blob is looked up once per document (on open and after
+/// save) rather than on every keystroke, since it only changes when HEAD
+/// itself moves.
+pub struct DiffProvider {
+    repo: Option<git2::Repository>,
+    path: PathBuf,
+    head_text: Option<String>,
+}
+
+impl DiffProvider {
+    /// Set up a provider for a document backed by `path`. If `path` isn't
+    /// inside a git repository, every line is reported as [DiffKind::Added].
+    pub fn for_path(path: &Path) -> Self {
+        let mut provider = DiffProvider {
+            repo: git2::Repository::discover(path).ok(),
+            path: path.to_owned(),
+            head_text: None,
+        };
+        provider.refresh_head_blob();
+        provider
+    }
+
+    /// Re-read the blob for this document's path at HEAD. Call this after
+    /// HEAD is expected to have moved, e.g. right after a save.
+    pub fn refresh_head_blob(&mut self) {
+        self.head_text = self.read_head_blob();
+    }
+
+    fn read_head_blob(&self) -> Option<String> {
+        let repo = self.repo.as_ref()?;
+        let workdir = repo.workdir()?;
+        let rel_path = self.path.strip_prefix(workdir).ok()?;
+        let head_tree = repo.head().ok()?.peel_to_tree().ok()?;
+        let entry = head_tree.get_path(rel_path).ok()?;
+        let blob = entry.to_object(repo).ok()?.peel_to_blob().ok()?;
+        Some(String::from_utf8_lossy(blob.content()).into_owned())
+    }
+
+    /// Diff `current_text` against the cached HEAD blob.
+    pub fn diff(&self, current_text: &str) -> Vec<DiffHunk> {
+        let old: Vec<&str> = self.head_text.as_deref().unwrap_or("").lines().collect();
+        let new: Vec<&str> = current_text.lines().collect();
+        if self.head_text.is_none() {
+            return (0..new.len())
+                .map(|line| DiffHunk {
+                    line,
+                    kind: DiffKind::Added,
+                })
+                .collect();
+        }
+        diff_lines(&old, &new)
+    }
+}
+
+/// A minimal LCS-based line diff. Good enough for gutter markers on
+/// editor-sized files; not intended for huge files or binary content.
+fn diff_lines(old: &[&str], new: &[&str]) -> Vec<DiffHunk> {
+    let (old_len, new_len) = (old.len(), new.len());
+    let mut lcs = vec![vec![0u32; new_len + 1]; old_len + 1];
+    for i in (0..old_len).rev() {
+        for j in (0..new_len).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut hunks = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    let mut pending_deletes = 0;
+    while i < old_len && j < new_len {
+        if old[i] == new[j] {
+            if pending_deletes > 0 {
+                hunks.push(DiffHunk {
+                    line: j,
+                    kind: DiffKind::Deleted,
+                });
+                pending_deletes = 0;
+            }
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            i += 1;
+            pending_deletes += 1;
+        } else {
+            let kind = if pending_deletes > 0 {
+                pending_deletes -= 1;
+                DiffKind::Modified
+            } else {
+                DiffKind::Added
+            };
+            hunks.push(DiffHunk { line: j, kind });
+            j += 1;
+        }
+    }
+    if pending_deletes > 0 || i < old_len {
+        hunks.push(DiffHunk {
+            line: new_len,
+            kind: DiffKind::Deleted,
+        });
+    }
+    while j < new_len {
+        hunks.push(DiffHunk {
+            line: j,
+            kind: DiffKind::Added,
+        });
+        j += 1;
+    }
+    hunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(hunks: &[DiffHunk]) -> Vec<(usize, DiffKind)> {
+        hunks.iter().map(|h| (h.line, h.kind)).collect()
+    }
+
+    #[test]
+    fn identical_text_has_no_hunks() {
+        let hunks = diff_lines(&["a", "b"], &["a", "b"]);
+        assert!(hunks.is_empty());
+    }
+
+    #[test]
+    fn inserted_line_is_added() {
+        let hunks = diff_lines(&["a", "b"], &["a", "x", "b"]);
+        assert_eq!(lines(&hunks), vec![(1, DiffKind::Added)]);
+    }
+
+    #[test]
+    fn removed_line_is_deleted() {
+        let hunks = diff_lines(&["a", "b", "c"], &["a", "c"]);
+        assert_eq!(lines(&hunks), vec![(1, DiffKind::Deleted)]);
+    }
+
+    #[test]
+    fn changed_line_is_modified() {
+        let hunks = diff_lines(&["a", "b", "c"], &["a", "x", "c"]);
+        assert_eq!(lines(&hunks), vec![(1, DiffKind::Modified)]);
+    }
+}
@@ -0,0 +1,236 @@
+//! An open document: its CRDT-backed text buffer, and where (if anywhere)
+//! it's backed by a file on disk.
+
+use std::path::PathBuf;
+
+use bazed_rpc::core_proto::{CaretPosition, TextChange, ToFrontend};
+use color_eyre::Result;
+use uuid::Uuid;
+
+use crate::{
+    crdt::CrdtBuffer,
+    user_buffer_op::{EditOp, MovementOp},
+    view::{View, ViewId},
+};
+
+/// Identifies an open document for the lifetime of the core process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DocumentId(pub Uuid);
+
+impl DocumentId {
+    pub fn gen() -> Self {
+        DocumentId(Uuid::new_v4())
+    }
+
+    pub fn from_uuid(id: Uuid) -> Self {
+        DocumentId(id)
+    }
+}
+
+impl std::fmt::Display for DocumentId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The document's content, backed by a [CrdtBuffer] so concurrent edits
+/// merge without conflicting, plus a simple snapshot-based undo/redo
+/// history. Tracks its own caret rather than each view having one, since
+/// editing is always relative to one logical position even when multiple
+/// views are scrolled to different parts of the document.
+pub struct Buffer {
+    crdt: CrdtBuffer,
+    caret: usize,
+    undo_stack: Vec<String>,
+    redo_stack: Vec<String>,
+}
+
+impl Buffer {
+    fn from_str(text: &str) -> Self {
+        Buffer {
+            crdt: CrdtBuffer::from_str(Uuid::new_v4(), text),
+            caret: 0,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+        }
+    }
+
+    pub fn content_to_string(&self) -> String {
+        self.crdt.content_to_string()
+    }
+
+    pub fn line(&self, line: usize) -> String {
+        self.content_to_string()
+            .lines()
+            .nth(line)
+            .unwrap_or_default()
+            .to_owned()
+    }
+
+    fn push_undo_snapshot(&mut self) {
+        self.undo_stack.push(self.content_to_string());
+        self.redo_stack.clear();
+    }
+
+    /// Apply a local edit typed by this client at the buffer's caret.
+    pub fn apply_edit_op(&mut self, op: EditOp) {
+        self.push_undo_snapshot();
+        match op {
+            EditOp::InsertChar(ch) => {
+                self.crdt.local_insert(self.caret, ch);
+                self.caret += 1;
+            },
+            EditOp::DeleteBackward => {
+                if self.caret > 0 {
+                    self.caret -= 1;
+                    self.crdt.local_delete(self.caret);
+                }
+            },
+            EditOp::DeleteForward => {
+                self.crdt.local_delete(self.caret);
+            },
+        }
+    }
+
+    /// Remove the given line, returning its text (without the trailing
+    /// newline) so it can be stored in a register.
+    pub fn delete_line(&mut self, line: usize) -> String {
+        self.push_undo_snapshot();
+        let content = self.content_to_string();
+        let Some(text) = content.lines().nth(line).map(str::to_owned) else {
+            return String::new();
+        };
+        let start = Self::char_index_of_line(&content, line);
+        let has_trailing_newline = start + text.chars().count() < content.chars().count();
+        let delete_count = text.chars().count() + usize::from(has_trailing_newline);
+        for _ in 0..delete_count {
+            self.crdt.local_delete(start);
+        }
+        text
+    }
+
+    fn char_index_of_line(content: &str, line: usize) -> usize {
+        let mut index = 0;
+        for (line_no, text) in content.split('\n').enumerate() {
+            if line_no == line {
+                return index;
+            }
+            index += text.chars().count() + 1;
+        }
+        index
+    }
+
+    /// Insert `text` at the caret, e.g. when pasting from a register.
+    pub fn insert_text_at_caret(&mut self, text: &str) {
+        self.push_undo_snapshot();
+        for ch in text.chars() {
+            self.crdt.local_insert(self.caret, ch);
+            self.caret += 1;
+        }
+    }
+
+    /// Merge a remote edit, translating its absolute line/col range into a
+    /// delete-then-insert against the CRDT buffer.
+    pub fn apply_text_change(&mut self, site_id: Uuid, change: &TextChange) {
+        self.push_undo_snapshot();
+        let content = self.content_to_string();
+        let start = Self::char_index(&content, &change.range.0);
+        let end = Self::char_index(&content, &change.range.1);
+        for _ in start..end {
+            self.crdt.local_delete(start);
+        }
+        for (index, ch) in (start..).zip(change.new_text.chars()) {
+            self.crdt.insert_attributed_to(index, ch, site_id);
+        }
+    }
+
+    fn char_index(content: &str, pos: &CaretPosition) -> usize {
+        let mut index = Self::char_index_of_line(content, pos.line);
+        if let Some(line) = content.split('\n').nth(pos.line) {
+            index += pos.col.min(line.chars().count());
+        }
+        index
+    }
+
+    pub fn apply_movement_op(&mut self, view: &mut View, op: MovementOp) {
+        match op {
+            MovementOp::Left => view.first_col = view.first_col.saturating_sub(1),
+            MovementOp::Right => view.first_col += 1,
+            MovementOp::Up => view.first_line = view.first_line.saturating_sub(1),
+            MovementOp::Down => view.first_line += 1,
+        }
+    }
+
+    /// Revert to the state before the last edit. Returns whether there was
+    /// anything to undo; the caller bumps the save revision itself, the
+    /// same as for any other mutation, rather than this reporting one of
+    /// its own — the undo-stack depth and the save revision counter are
+    /// different numbering schemes and aren't interchangeable.
+    pub fn undo(&mut self) -> bool {
+        let Some(previous) = self.undo_stack.pop() else {
+            return false;
+        };
+        self.redo_stack.push(self.content_to_string());
+        self.crdt = CrdtBuffer::from_str(self.crdt.site_id(), &previous);
+        true
+    }
+
+    pub fn redo(&mut self) -> bool {
+        let Some(next) = self.redo_stack.pop() else {
+            return false;
+        };
+        self.undo_stack.push(self.content_to_string());
+        self.crdt = CrdtBuffer::from_str(self.crdt.site_id(), &next);
+        true
+    }
+}
+
+/// An open document: its buffer, and the file it was loaded from, if any.
+pub struct Document {
+    pub path: Option<PathBuf>,
+    pub buffer: Buffer,
+}
+
+impl Document {
+    pub fn open_file(path: PathBuf) -> Result<Self> {
+        let text = std::fs::read_to_string(&path).unwrap_or_default();
+        Ok(Document {
+            buffer: Buffer::from_str(&text),
+            path: Some(path),
+        })
+    }
+
+    pub fn open_ephemeral() -> Self {
+        Document {
+            buffer: Buffer::from_str(""),
+            path: None,
+        }
+    }
+
+    /// Merge a remote edit, tagging the CRDT ops it produces with `site_id`.
+    pub fn apply_crdt_change(&mut self, site_id: Uuid, change: &TextChange) {
+        self.buffer.apply_text_change(site_id, change);
+    }
+
+    /// Build the [ToFrontend::UpdateView] notification for `view`'s current
+    /// viewport onto this document.
+    pub fn create_update_notification(&self, view_id: ViewId, view: &View, modified: bool) -> ToFrontend {
+        let full_text = self.buffer.content_to_string();
+        let text = full_text
+            .lines()
+            .skip(view.first_line)
+            .take(view.height)
+            .map(str::to_owned)
+            .collect();
+        ToFrontend::UpdateView {
+            view_id: view_id.into(),
+            first_line: view.first_line,
+            scroll_fraction: view.scroll_fraction,
+            height: view.height,
+            text,
+            carets: Vec::new(),
+            vim_mode: "normal".to_owned(),
+            modified,
+        }
+    }
+}
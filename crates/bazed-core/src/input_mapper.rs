@@ -0,0 +1,46 @@
+//! Translates a raw [KeyInput] into an [Operation], independent of any
+//! particular document or view. Deliberately minimal: a handful of direct
+//! chords rather than full vim-style modal/prefix sequences, since that
+//! would need state this function doesn't have access to.
+
+use bazed_rpc::keycode::{Key, KeyInput};
+
+use crate::{
+    registers::{RegisterName, RegisterOp},
+    user_buffer_op::{DocumentOp, EditOp, MovementOp, Operation},
+};
+
+pub fn interpret_key_input(input: &KeyInput) -> Option<Operation> {
+    let m = input.modifiers;
+    match input.key {
+        Key::Char('s') if m.ctrl => Some(Operation::Document(DocumentOp::Save)),
+        Key::Char('z') if m.ctrl && m.shift => Some(Operation::Document(DocumentOp::Redo)),
+        Key::Char('z') if m.ctrl => Some(Operation::Document(DocumentOp::Undo)),
+        // Ctrl+Shift+C/X/V, the conventional binding for the real OS
+        // clipboard (plain Ctrl+C/X/V stay on the in-core unnamed
+        // register, since Ctrl+C/V are already muscle memory for
+        // copy/paste within the editor).
+        Key::Char('c') if m.ctrl && m.shift => {
+            Some(Operation::Register(RegisterOp::Yank(RegisterName::Named('+'))))
+        },
+        Key::Char('x') if m.ctrl && m.shift => {
+            Some(Operation::Register(RegisterOp::Delete(RegisterName::Named('+'))))
+        },
+        Key::Char('v') if m.ctrl && m.shift => {
+            Some(Operation::Register(RegisterOp::Paste(RegisterName::Named('+'))))
+        },
+        Key::Char('c') if m.ctrl => Some(Operation::Register(RegisterOp::Yank(RegisterName::Unnamed))),
+        Key::Char('x') if m.ctrl => Some(Operation::Register(RegisterOp::Delete(RegisterName::Unnamed))),
+        Key::Char('v') if m.ctrl => Some(Operation::Register(RegisterOp::Paste(RegisterName::Unnamed))),
+        Key::Char(ch) if !m.ctrl && !m.alt => Some(Operation::Edit(EditOp::InsertChar(ch))),
+        Key::Enter => Some(Operation::Edit(EditOp::InsertChar('\n'))),
+        Key::Tab => Some(Operation::Edit(EditOp::InsertChar('\t'))),
+        Key::Backspace => Some(Operation::Edit(EditOp::DeleteBackward)),
+        Key::Delete => Some(Operation::Edit(EditOp::DeleteForward)),
+        Key::Left => Some(Operation::Movement(MovementOp::Left)),
+        Key::Right => Some(Operation::Movement(MovementOp::Right)),
+        Key::Up => Some(Operation::Movement(MovementOp::Up)),
+        Key::Down => Some(Operation::Movement(MovementOp::Down)),
+        Key::Escape | Key::Char(_) => None,
+    }
+}
@@ -0,0 +1,11 @@
+pub mod app;
+mod crdt;
+mod diff;
+pub mod document;
+mod input_mapper;
+mod lsp;
+mod registers;
+mod save;
+mod tree;
+pub mod view;
+mod user_buffer_op;
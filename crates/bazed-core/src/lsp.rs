@@ -0,0 +1,410 @@
+//! Language server management: spawns and talks to one language server per
+//! language (detected from a document's file extension), and translates
+//! between bazed's absolute line/col positions and LSP's UTF-16 based ones.
+
+use std::{
+    collections::HashMap,
+    path::Path,
+    process::Stdio,
+    sync::Arc,
+};
+
+use bazed_rpc::core_proto::{CaretPosition, CompletionItem, Diagnostic, DiagnosticSeverity};
+use color_eyre::Result;
+use serde_json::{json, Value};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    process::{Child, ChildStdin, Command},
+    sync::{mpsc, Mutex},
+};
+use uuid::Uuid;
+
+use crate::document::DocumentId;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("No language server configured for language {0:?}")]
+    UnknownLanguage(String),
+    #[error("Language server for {0:?} has no stdin pipe")]
+    NoStdin(String),
+}
+
+/// Identifies a language by the key used to look up and launch its server,
+/// e.g. `"rust"` or `"python"`.
+pub type LanguageId = String;
+
+/// Detect the language of a document from its file extension. Documents
+/// with an unknown or missing extension get no language server.
+pub fn detect_language(path: &Path) -> Option<LanguageId> {
+    let lang = match path.extension()?.to_str()? {
+        "rs" => "rust",
+        "py" => "python",
+        "ts" | "tsx" => "typescript",
+        "js" | "jsx" => "javascript",
+        "go" => "go",
+        _ => return None,
+    };
+    Some(lang.to_string())
+}
+
+fn server_command(language: &str) -> Option<(&'static str, &'static [&'static str])> {
+    match language {
+        "rust" => Some(("rust-analyzer", &[])),
+        "python" => Some(("pylsp", &[])),
+        "typescript" | "javascript" => Some(("typescript-language-server", &["--stdio"])),
+        "go" => Some(("gopls", &[])),
+        _ => None,
+    }
+}
+
+/// Something a language server told us, relayed by the reader task since it
+/// runs independently of the RPC handling loop.
+#[derive(Debug)]
+pub enum LspEvent {
+    Diagnostics {
+        document_id: DocumentId,
+        diagnostics: Vec<Diagnostic>,
+    },
+    Completion {
+        request_id: Uuid,
+        items: Vec<CompletionItem>,
+    },
+}
+
+/// Maps a language server's own notion of a document (its `uri`) back to
+/// ours, and remembers the last text we sent it, since LSP positions are
+/// UTF-16 offsets into a specific line and converting them back needs that
+/// line's text. Shared between [LspRegistry] and every server's reader task.
+#[derive(Default)]
+struct DocumentTracking {
+    document_ids: HashMap<String, DocumentId>,
+    last_known_text: HashMap<String, String>,
+}
+
+/// A running language server process, spoken to over JSON-RPC framed with
+/// `Content-Length` headers, per the LSP base protocol.
+struct LspServer {
+    stdin: ChildStdin,
+    _child: Child,
+    next_id: i64,
+    /// Maps this server's own JSON-RPC request ids back to the frontend
+    /// request id waiting on them, so the reader task can answer the right
+    /// one once the server's response comes in. Shared with the reader task,
+    /// which removes entries as responses arrive.
+    pending_completions: Arc<Mutex<HashMap<i64, Uuid>>>,
+}
+
+impl LspServer {
+    async fn spawn(
+        language: &str,
+        events: mpsc::UnboundedSender<LspEvent>,
+        tracking: Arc<Mutex<DocumentTracking>>,
+    ) -> Result<Self> {
+        let (cmd, args) =
+            server_command(language).ok_or_else(|| Error::UnknownLanguage(language.to_owned()))?;
+        let mut child = Command::new(cmd)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| Error::NoStdin(language.to_owned()))?;
+        let stdout = child.stdout.take().ok_or_else(|| Error::NoStdin(language.to_owned()))?;
+        let pending_completions = Arc::new(Mutex::new(HashMap::new()));
+        tokio::spawn(read_messages(
+            BufReader::new(stdout),
+            events,
+            tracking,
+            pending_completions.clone(),
+        ));
+        Ok(LspServer {
+            stdin,
+            _child: child,
+            next_id: 0,
+            pending_completions,
+        })
+    }
+
+    async fn notify(&mut self, method: &str, params: Value) -> Result<()> {
+        self.write_message(json!({ "jsonrpc": "2.0", "method": method, "params": params }))
+            .await
+    }
+
+    async fn request(&mut self, method: &str, params: Value) -> Result<i64> {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.write_message(
+            json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }),
+        )
+        .await?;
+        Ok(id)
+    }
+
+    async fn write_message(&mut self, message: Value) -> Result<()> {
+        let body = serde_json::to_vec(&message)?;
+        self.stdin
+            .write_all(format!("Content-Length: {}\r\n\r\n", body.len()).as_bytes())
+            .await?;
+        self.stdin.write_all(&body).await?;
+        Ok(())
+    }
+}
+
+/// Read one `Content-Length`-framed message body, or `None` once the stream
+/// ends.
+async fn read_one_message<R: tokio::io::AsyncBufRead + Unpin>(reader: &mut R) -> Option<Vec<u8>> {
+    let mut content_length = None;
+    let mut header = String::new();
+    loop {
+        header.clear();
+        if reader.read_line(&mut header).await.unwrap_or(0) == 0 {
+            return None;
+        }
+        let header = header.trim();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(len) = header.strip_prefix("Content-Length: ") {
+            content_length = len.trim().parse().ok();
+        }
+    }
+    let len = content_length?;
+    let mut body = vec![0u8; len];
+    tokio::io::AsyncReadExt::read_exact(reader, &mut body)
+        .await
+        .ok()?;
+    Some(body)
+}
+
+/// Reads `Content-Length`-framed JSON-RPC messages from a server's stdout
+/// until it exits, translating `textDocument/publishDiagnostics`
+/// notifications and completion responses into [LspEvent]s.
+async fn read_messages<R: tokio::io::AsyncBufRead + Unpin>(
+    mut reader: R,
+    events: mpsc::UnboundedSender<LspEvent>,
+    tracking: Arc<Mutex<DocumentTracking>>,
+    pending_completions: Arc<Mutex<HashMap<i64, Uuid>>>,
+) {
+    while let Some(body) = read_one_message(&mut reader).await {
+        let Ok(message) = serde_json::from_slice::<Value>(&body) else {
+            continue;
+        };
+
+        if message.get("method").and_then(Value::as_str) == Some("textDocument/publishDiagnostics") {
+            let Some(params) = message.get("params") else { continue };
+            let (Some(uri), Some(raw_diagnostics)) = (
+                params.get("uri").and_then(Value::as_str),
+                params.get("diagnostics").and_then(Value::as_array),
+            ) else {
+                continue;
+            };
+            let tracking = tracking.lock().await;
+            let Some(&document_id) = tracking.document_ids.get(uri) else { continue };
+            let text = tracking.last_known_text.get(uri).cloned().unwrap_or_default();
+            let diagnostics = raw_diagnostics
+                .iter()
+                .filter_map(|d| parse_diagnostic(d, &text))
+                .collect();
+            let _ = events.send(LspEvent::Diagnostics { document_id, diagnostics });
+            continue;
+        }
+
+        if let Some(id) = message.get("id").and_then(Value::as_i64) {
+            let request_id = pending_completions.lock().await.remove(&id);
+            let Some(request_id) = request_id else { continue };
+            let items = message
+                .get("result")
+                .and_then(|result| result.get("items").or(Some(result)))
+                .and_then(Value::as_array)
+                .map(|items| items.iter().filter_map(parse_completion_item).collect())
+                .unwrap_or_default();
+            let _ = events.send(LspEvent::Completion { request_id, items });
+        }
+    }
+}
+
+fn parse_diagnostic(raw: &Value, text: &str) -> Option<Diagnostic> {
+    let range = raw.get("range")?;
+    let start = parse_lsp_position(range.get("start")?, text)?;
+    let end = parse_lsp_position(range.get("end")?, text)?;
+    let severity = severity_from_lsp(raw.get("severity").and_then(Value::as_i64));
+    let message = raw.get("message").and_then(Value::as_str)?.to_owned();
+    Some(Diagnostic {
+        range: (start, end),
+        severity,
+        message,
+    })
+}
+
+fn parse_lsp_position(raw: &Value, text: &str) -> Option<CaretPosition> {
+    let line = raw.get("line").and_then(Value::as_u64)? as usize;
+    let character = raw.get("character").and_then(Value::as_u64)? as usize;
+    let line_text = text.lines().nth(line).unwrap_or_default();
+    Some(from_lsp_position(line_text, line, character))
+}
+
+fn parse_completion_item(raw: &Value) -> Option<CompletionItem> {
+    let label = raw.get("label").and_then(Value::as_str)?.to_owned();
+    let detail = raw.get("detail").and_then(Value::as_str).map(str::to_owned);
+    let insert_text = raw
+        .get("insertText")
+        .and_then(Value::as_str)
+        .unwrap_or(&label)
+        .to_owned();
+    Some(CompletionItem {
+        label,
+        detail,
+        insert_text,
+    })
+}
+
+/// Converts between bazed's absolute line/col positions and LSP's UTF-16
+/// code-unit based ones for a given line of text.
+pub fn to_lsp_position(line_text: &str, pos: &CaretPosition) -> (usize, usize) {
+    let utf16_col = line_text.chars().take(pos.col).map(char::len_utf16).sum();
+    (pos.line, utf16_col)
+}
+
+pub fn from_lsp_position(line_text: &str, line: usize, utf16_col: usize) -> CaretPosition {
+    let mut seen_utf16 = 0;
+    let mut col = 0;
+    for ch in line_text.chars() {
+        if seen_utf16 >= utf16_col {
+            break;
+        }
+        seen_utf16 += ch.len_utf16();
+        col += 1;
+    }
+    CaretPosition { line, col }
+}
+
+pub(crate) fn severity_from_lsp(severity: Option<i64>) -> DiagnosticSeverity {
+    match severity {
+        Some(1) => DiagnosticSeverity::Error,
+        Some(2) => DiagnosticSeverity::Warning,
+        Some(3) => DiagnosticSeverity::Information,
+        _ => DiagnosticSeverity::Hint,
+    }
+}
+
+/// Keeps one running [LspServer] per language, starting it lazily the first
+/// time a document of that language is opened.
+pub struct LspRegistry {
+    servers: HashMap<LanguageId, LspServer>,
+    events: mpsc::UnboundedSender<LspEvent>,
+    tracking: Arc<Mutex<DocumentTracking>>,
+    versions: HashMap<String, i64>,
+    last_sent: HashMap<String, std::time::Instant>,
+}
+
+/// Minimum time between two `didChange` notifications for the same
+/// document, so that fast typing doesn't flood the language server with one
+/// notification per keystroke.
+const DID_CHANGE_DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(300);
+
+impl LspRegistry {
+    pub fn new(events: mpsc::UnboundedSender<LspEvent>) -> Self {
+        LspRegistry {
+            servers: HashMap::new(),
+            events,
+            tracking: Arc::new(Mutex::new(DocumentTracking::default())),
+            versions: HashMap::new(),
+            last_sent: HashMap::new(),
+        }
+    }
+
+    async fn server_for(&mut self, language: &LanguageId) -> Result<&mut LspServer> {
+        if !self.servers.contains_key(language) {
+            let server = LspServer::spawn(language, self.events.clone(), self.tracking.clone()).await?;
+            self.servers.insert(language.clone(), server);
+        }
+        Ok(self.servers.get_mut(language).expect("just inserted"))
+    }
+
+    /// Start or reuse the server for `language` and notify it that a
+    /// document was opened.
+    pub async fn did_open(
+        &mut self,
+        language: &LanguageId,
+        uri: &str,
+        document_id: DocumentId,
+        text: &str,
+    ) -> Result<()> {
+        {
+            let mut tracking = self.tracking.lock().await;
+            tracking.document_ids.insert(uri.to_owned(), document_id);
+            tracking.last_known_text.insert(uri.to_owned(), text.to_owned());
+        }
+        self.server_for(language)
+            .await?
+            .notify(
+                "textDocument/didOpen",
+                json!({
+                    "textDocument": {
+                        "uri": uri,
+                        "languageId": language,
+                        "version": 0,
+                        "text": text,
+                    }
+                }),
+            )
+            .await
+    }
+
+    /// Forward a change to the document's server, unless one was already
+    /// sent for this document within [`DID_CHANGE_DEBOUNCE`].
+    pub async fn did_change(&mut self, language: &LanguageId, uri: &str, full_text: &str) -> Result<()> {
+        self.tracking
+            .lock()
+            .await
+            .last_known_text
+            .insert(uri.to_owned(), full_text.to_owned());
+        if let Some(last) = self.last_sent.get(uri) {
+            if last.elapsed() < DID_CHANGE_DEBOUNCE {
+                return Ok(());
+            }
+        }
+        let version = self.versions.entry(uri.to_owned()).or_insert(0);
+        *version += 1;
+        let version = *version;
+        self.last_sent.insert(uri.to_owned(), std::time::Instant::now());
+        self.server_for(language)
+            .await?
+            .notify(
+                "textDocument/didChange",
+                json!({
+                    "textDocument": { "uri": uri, "version": version },
+                    "contentChanges": [{ "text": full_text }],
+                }),
+            )
+            .await
+    }
+
+    /// Request completions at `line`/`utf16_col`, tagging the request with
+    /// `request_id` so the reader task can answer the right frontend request
+    /// once the server's response arrives.
+    pub async fn request_completion(
+        &mut self,
+        language: &LanguageId,
+        uri: &str,
+        line: usize,
+        utf16_col: usize,
+        request_id: Uuid,
+    ) -> Result<()> {
+        let server = self.server_for(language).await?;
+        let lsp_id = server
+            .request(
+                "textDocument/completion",
+                json!({
+                    "textDocument": { "uri": uri },
+                    "position": { "line": line, "character": utf16_col },
+                }),
+            )
+            .await?;
+        server.pending_completions.lock().await.insert(lsp_id, request_id);
+        Ok(())
+    }
+}
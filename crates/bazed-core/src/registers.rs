@@ -0,0 +1,90 @@
+//! Vim-style yank/delete/paste registers. Named registers (`"a` through
+//! `"z`, ...) and the unnamed register live entirely in the core. The `"*"`
+//! and `"+"` registers instead proxy to the real OS clipboard, which only
+//! the frontend has access to, via [ToBackend::ClipboardContent] and
+//! [ToFrontend::RequestClipboard]/[ToFrontend::SetClipboard].
+
+use std::collections::HashMap;
+
+use uuid::Uuid;
+
+use crate::view::ViewId;
+
+/// Name of a register: either a named register like `'a'`, or the unnamed
+/// register used when no register is explicitly specified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RegisterName {
+    Unnamed,
+    Named(char),
+}
+
+impl RegisterName {
+    /// Whether this register is backed by the real OS clipboard rather than
+    /// in-core storage.
+    pub fn is_clipboard(&self) -> bool {
+        matches!(self, RegisterName::Named('*') | RegisterName::Named('+'))
+    }
+}
+
+/// A register operation produced by [crate::input_mapper::interpret_key_input],
+/// carried as an [crate::user_buffer_op::Operation::Register] variant. Only
+/// names the register and the intent; `input_mapper` sees nothing but the
+/// raw key, so it can't resolve what text a yank/delete would affect. The
+/// app, which owns the document, fills that in while handling the op.
+#[derive(Debug, Clone, Copy)]
+pub enum RegisterOp {
+    /// Yank (copy) the current line into `register` without removing it.
+    Yank(RegisterName),
+    /// Delete the current line into `register`.
+    Delete(RegisterName),
+    /// Insert the contents of `register` at the caret.
+    Paste(RegisterName),
+}
+
+/// In-core storage for every register except the clipboard-backed ones.
+pub struct Registers {
+    contents: HashMap<RegisterName, String>,
+    /// Paste requests waiting on [ToBackend::ClipboardContent] for the
+    /// system clipboard, keyed by the request id sent in
+    /// [ToFrontend::RequestClipboard] and naming the view to paste into once
+    /// the content arrives.
+    pending_clipboard_reads: HashMap<Uuid, ViewId>,
+}
+
+impl Registers {
+    pub fn new() -> Self {
+        Registers {
+            contents: HashMap::new(),
+            pending_clipboard_reads: HashMap::new(),
+        }
+    }
+
+    /// Store `text` in `register`. For clipboard registers, the caller is
+    /// expected to also send [crate::app::App]'s `ToFrontend::SetClipboard`;
+    /// this only updates in-core storage.
+    pub fn set(&mut self, register: RegisterName, text: String) {
+        self.contents.insert(register, text);
+    }
+
+    pub fn get(&self, register: RegisterName) -> Option<&str> {
+        self.contents.get(&register).map(String::as_str)
+    }
+
+    pub fn track_clipboard_read(&mut self, request_id: Uuid, view_id: ViewId) {
+        self.pending_clipboard_reads.insert(request_id, view_id);
+    }
+
+    /// Resolve a pending clipboard read, returning the view to paste `text`
+    /// into if `request_id` corresponded to one we were waiting on.
+    pub fn resolve_clipboard_read(&mut self, request_id: Uuid, text: String) -> Option<ViewId> {
+        let view_id = self.pending_clipboard_reads.remove(&request_id)?;
+        self.set(RegisterName::Named('*'), text);
+        Some(view_id)
+    }
+}
+
+impl Default for Registers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
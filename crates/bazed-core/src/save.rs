@@ -0,0 +1,86 @@
+//! Asynchronous document saving. A save job captures the buffer content at
+//! a given revision and writes it on its own task, so it never blocks the
+//! RPC handling loop (and by extension every other client) while the disk
+//! write is in flight.
+
+use std::{collections::HashMap, path::PathBuf};
+
+use tokio::sync::mpsc;
+
+use crate::document::DocumentId;
+
+/// Outcome of a save job, reported back once the write completes.
+#[derive(Debug)]
+pub enum SaveResult {
+    Saved { document_id: DocumentId, revision: u64 },
+    Failed { document_id: DocumentId, error: String },
+}
+
+/// Tracks each open document's buffer revision and the revision that was
+/// last written to disk, so a modified/clean indicator can be derived by
+/// comparing the two, and drives the save jobs themselves.
+pub struct SaveTracker {
+    revisions: HashMap<DocumentId, u64>,
+    saved_revisions: HashMap<DocumentId, u64>,
+    results: mpsc::UnboundedSender<SaveResult>,
+}
+
+impl SaveTracker {
+    pub fn new(results: mpsc::UnboundedSender<SaveResult>) -> Self {
+        SaveTracker {
+            revisions: HashMap::new(),
+            saved_revisions: HashMap::new(),
+            results,
+        }
+    }
+
+    /// Call after every edit to `document_id` to advance its revision, and
+    /// get the new revision back.
+    pub fn bump_revision(&mut self, document_id: DocumentId) -> u64 {
+        let revision = self.revisions.entry(document_id).or_insert(0);
+        *revision += 1;
+        *revision
+    }
+
+    pub fn current_revision(&self, document_id: DocumentId) -> u64 {
+        *self.revisions.get(&document_id).unwrap_or(&0)
+    }
+
+    /// Whether `document_id` has edits since the last successful save.
+    pub fn is_modified(&self, document_id: DocumentId) -> bool {
+        self.current_revision(document_id) != *self.saved_revisions.get(&document_id).unwrap_or(&0)
+    }
+
+    /// Spawn a job that writes `text`, the buffer content as of the
+    /// document's current revision, to `path`. The outcome is delivered
+    /// later through the `results` channel passed to [SaveTracker::new].
+    pub fn spawn_save(&mut self, document_id: DocumentId, path: Option<PathBuf>, text: String) {
+        let revision = self.current_revision(document_id);
+        let results = self.results.clone();
+        tokio::spawn(async move {
+            let outcome = match path {
+                Some(path) => match tokio::fs::write(&path, text).await {
+                    Ok(()) => SaveResult::Saved {
+                        document_id,
+                        revision,
+                    },
+                    Err(err) => SaveResult::Failed {
+                        document_id,
+                        error: err.to_string(),
+                    },
+                },
+                None => SaveResult::Failed {
+                    document_id,
+                    error: "document has no path to save to".to_owned(),
+                },
+            };
+            // The receiving end lives as long as the App that owns this
+            // tracker, so a closed channel only happens on shutdown.
+            let _ = results.send(outcome);
+        });
+    }
+
+    pub fn mark_saved(&mut self, document_id: DocumentId, revision: u64) {
+        self.saved_revisions.insert(document_id, revision);
+    }
+}
@@ -0,0 +1,339 @@
+//! Recursive window layout tree: leaves are views, containers split the
+//! space they occupy horizontally or vertically among their children. Lets
+//! a frontend render split panes instead of a single flat view.
+
+use bazed_rpc::core_proto;
+
+use crate::view::ViewId;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitDirection {
+    Horizontal,
+    Vertical,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FocusDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone)]
+pub enum LayoutNode {
+    Leaf(ViewId),
+    Split {
+        direction: SplitDirection,
+        children: Vec<LayoutNode>,
+    },
+}
+
+impl LayoutNode {
+    fn leaves(&self, out: &mut Vec<ViewId>) {
+        match self {
+            LayoutNode::Leaf(id) => out.push(*id),
+            LayoutNode::Split { children, .. } => {
+                for child in children {
+                    child.leaves(out);
+                }
+            },
+        }
+    }
+
+    /// Remove `target` from the tree. Returns `true` if it was found. A
+    /// split container left with a single child collapses into that child.
+    fn remove(&mut self, target: ViewId) -> bool {
+        let LayoutNode::Split { children, .. } = self else {
+            return false;
+        };
+        let found = if let Some(pos) = children
+            .iter()
+            .position(|c| matches!(c, LayoutNode::Leaf(id) if *id == target))
+        {
+            children.remove(pos);
+            true
+        } else {
+            children.iter_mut().any(|child| child.remove(target))
+        };
+        if found && children.len() == 1 {
+            *self = children.remove(0);
+        }
+        found
+    }
+
+    /// The path from this node down to `target`, as a sequence of child
+    /// indices, if `target` is anywhere beneath it.
+    fn path_to(&self, target: ViewId, path: &mut Vec<usize>) -> bool {
+        match self {
+            LayoutNode::Leaf(id) => *id == target,
+            LayoutNode::Split { children, .. } => {
+                for (index, child) in children.iter().enumerate() {
+                    path.push(index);
+                    if child.path_to(target, path) {
+                        return true;
+                    }
+                    path.pop();
+                }
+                false
+            },
+        }
+    }
+
+    fn child_at(&self, path: &[usize]) -> &LayoutNode {
+        let mut node = self;
+        for &index in path {
+            let LayoutNode::Split { children, .. } = node else {
+                unreachable!("path was built by path_to, which only descends into splits");
+            };
+            node = &children[index];
+        }
+        node
+    }
+
+    fn first_leaf(&self) -> ViewId {
+        match self {
+            LayoutNode::Leaf(id) => *id,
+            LayoutNode::Split { children, .. } => children[0].first_leaf(),
+        }
+    }
+}
+
+/// The layout of every view in one window, as a tree of splits.
+pub struct LayoutTree {
+    root: LayoutNode,
+    focused: ViewId,
+}
+
+impl LayoutTree {
+    pub fn new(root_view: ViewId) -> Self {
+        LayoutTree {
+            root: LayoutNode::Leaf(root_view),
+            focused: root_view,
+        }
+    }
+
+    pub fn focused(&self) -> ViewId {
+        self.focused
+    }
+
+    pub fn root(&self) -> &LayoutNode {
+        &self.root
+    }
+
+    pub fn views(&self) -> Vec<ViewId> {
+        let mut out = Vec::new();
+        self.root.leaves(&mut out);
+        out
+    }
+
+    /// Split `target`, placing `new_view` next to it in `direction`. Panes
+    /// already split in the same direction as `target` just gain a sibling;
+    /// otherwise a new nested container is introduced. Returns `false` if
+    /// `target` isn't in the tree.
+    pub fn split(&mut self, target: ViewId, direction: SplitDirection, new_view: ViewId) -> bool {
+        let found = Self::split_node(&mut self.root, target, direction, new_view);
+        if found {
+            self.focused = new_view;
+        }
+        found
+    }
+
+    fn split_node(
+        node: &mut LayoutNode,
+        target: ViewId,
+        direction: SplitDirection,
+        new_view: ViewId,
+    ) -> bool {
+        match node {
+            LayoutNode::Leaf(id) if *id == target => {
+                *node = LayoutNode::Split {
+                    direction,
+                    children: vec![LayoutNode::Leaf(*id), LayoutNode::Leaf(new_view)],
+                };
+                true
+            },
+            LayoutNode::Leaf(_) => false,
+            LayoutNode::Split {
+                direction: existing_direction,
+                children,
+            } => {
+                if let Some(pos) = children
+                    .iter()
+                    .position(|c| matches!(c, LayoutNode::Leaf(id) if *id == target))
+                {
+                    if *existing_direction == direction {
+                        children.insert(pos + 1, LayoutNode::Leaf(new_view));
+                    } else {
+                        children[pos] = LayoutNode::Split {
+                            direction,
+                            children: vec![LayoutNode::Leaf(target), LayoutNode::Leaf(new_view)],
+                        };
+                    }
+                    return true;
+                }
+                children
+                    .iter_mut()
+                    .any(|child| Self::split_node(child, target, direction, new_view))
+            },
+        }
+    }
+
+    /// Close `target`'s pane. If it was focused, focus moves to the first
+    /// remaining view.
+    pub fn close(&mut self, target: ViewId) -> bool {
+        let removed = self.root.remove(target);
+        if removed && self.focused == target {
+            if let Some(next) = self.views().first() {
+                self.focused = *next;
+            }
+        }
+        removed
+    }
+
+    /// Move focus to the neighboring pane in `direction`: the nearest
+    /// enclosing split on the matching axis (horizontal for Left/Right,
+    /// vertical for Up/Down) with a sibling in that direction. Returns
+    /// `None` if there's no such neighbor, e.g. moving Left from the
+    /// leftmost pane of the window.
+    pub fn move_focus(&mut self, direction: FocusDirection) -> Option<ViewId> {
+        let axis = match direction {
+            FocusDirection::Left | FocusDirection::Right => SplitDirection::Horizontal,
+            FocusDirection::Up | FocusDirection::Down => SplitDirection::Vertical,
+        };
+        let step: isize = match direction {
+            FocusDirection::Right | FocusDirection::Down => 1,
+            FocusDirection::Left | FocusDirection::Up => -1,
+        };
+
+        let mut path = Vec::new();
+        if !self.root.path_to(self.focused, &mut path) {
+            return None;
+        }
+
+        while let Some(child_index) = path.pop() {
+            let LayoutNode::Split { direction: split_axis, children } = self.root.child_at(&path) else {
+                unreachable!("path was built by path_to, which only descends into splits");
+            };
+            if *split_axis != axis {
+                continue;
+            }
+            let next_index = child_index as isize + step;
+            if next_index < 0 || next_index as usize >= children.len() {
+                continue;
+            }
+            let mut neighbor_path = path.clone();
+            neighbor_path.push(next_index as usize);
+            self.focused = self.root.child_at(&neighbor_path).first_leaf();
+            return Some(self.focused);
+        }
+        None
+    }
+}
+
+impl From<SplitDirection> for core_proto::SplitDirection {
+    fn from(direction: SplitDirection) -> Self {
+        match direction {
+            SplitDirection::Horizontal => core_proto::SplitDirection::Horizontal,
+            SplitDirection::Vertical => core_proto::SplitDirection::Vertical,
+        }
+    }
+}
+
+impl From<core_proto::SplitDirection> for SplitDirection {
+    fn from(direction: core_proto::SplitDirection) -> Self {
+        match direction {
+            core_proto::SplitDirection::Horizontal => SplitDirection::Horizontal,
+            core_proto::SplitDirection::Vertical => SplitDirection::Vertical,
+        }
+    }
+}
+
+impl From<core_proto::FocusDirection> for FocusDirection {
+    fn from(direction: core_proto::FocusDirection) -> Self {
+        match direction {
+            core_proto::FocusDirection::Left => FocusDirection::Left,
+            core_proto::FocusDirection::Right => FocusDirection::Right,
+            core_proto::FocusDirection::Up => FocusDirection::Up,
+            core_proto::FocusDirection::Down => FocusDirection::Down,
+        }
+    }
+}
+
+impl From<&LayoutNode> for core_proto::LayoutNode {
+    fn from(node: &LayoutNode) -> Self {
+        match node {
+            LayoutNode::Leaf(id) => core_proto::LayoutNode::Leaf { view_id: (*id).into() },
+            LayoutNode::Split { direction, children } => core_proto::LayoutNode::Split {
+                direction: (*direction).into(),
+                children: children.iter().map(core_proto::LayoutNode::from).collect(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn move_focus_within_horizontal_split() {
+        let left = ViewId::gen();
+        let right = ViewId::gen();
+        let mut tree = LayoutTree::new(left);
+        tree.split(left, SplitDirection::Horizontal, right);
+        assert_eq!(tree.focused(), right);
+
+        assert_eq!(tree.move_focus(FocusDirection::Left), Some(left));
+        assert_eq!(tree.focused(), left);
+        assert_eq!(tree.move_focus(FocusDirection::Right), Some(right));
+        assert_eq!(tree.focused(), right);
+    }
+
+    #[test]
+    fn move_focus_ignores_the_wrong_axis() {
+        let left = ViewId::gen();
+        let right = ViewId::gen();
+        let mut tree = LayoutTree::new(left);
+        tree.split(left, SplitDirection::Horizontal, right);
+
+        // The split is horizontal, so there's no vertical neighbor for
+        // either pane.
+        assert_eq!(tree.move_focus(FocusDirection::Up), None);
+        assert_eq!(tree.move_focus(FocusDirection::Down), None);
+        assert_eq!(tree.focused(), right);
+    }
+
+    #[test]
+    fn move_focus_has_no_neighbor_past_the_edge() {
+        let left = ViewId::gen();
+        let right = ViewId::gen();
+        let mut tree = LayoutTree::new(left);
+        tree.split(left, SplitDirection::Horizontal, right);
+
+        tree.move_focus(FocusDirection::Left);
+        assert_eq!(tree.focused(), left);
+        assert_eq!(tree.move_focus(FocusDirection::Left), None);
+        assert_eq!(tree.focused(), left);
+    }
+
+    #[test]
+    fn move_focus_crosses_into_a_nested_split() {
+        // [ a | [ b / c ] ]: a horizontal split whose right child is itself
+        // a vertical split of b over c.
+        let a = ViewId::gen();
+        let b = ViewId::gen();
+        let c = ViewId::gen();
+        let mut tree = LayoutTree::new(a);
+        tree.split(a, SplitDirection::Horizontal, b);
+        tree.split(b, SplitDirection::Vertical, c);
+        assert_eq!(tree.focused(), c);
+
+        // Moving left out of the nested vertical split re-enters the outer
+        // horizontal split and lands on its first leaf.
+        assert_eq!(tree.move_focus(FocusDirection::Left), Some(a));
+        assert_eq!(tree.focused(), a);
+        assert_eq!(tree.move_focus(FocusDirection::Right), Some(b));
+        assert_eq!(tree.focused(), b);
+    }
+}
@@ -0,0 +1,43 @@
+//! The operations [crate::input_mapper::interpret_key_input] can produce
+//! from a key press, and that [crate::app::App::handle_key_pressed] executes
+//! against a document/view pair.
+
+use crate::registers::RegisterOp;
+
+/// An operation on the document itself, rather than its content.
+#[derive(Debug, Clone, Copy)]
+pub enum DocumentOp {
+    Save,
+    Undo,
+    Redo,
+}
+
+/// An edit to the buffer's content, applied at the buffer's own caret
+/// position (see [crate::document::Buffer]).
+#[derive(Debug, Clone, Copy)]
+pub enum EditOp {
+    InsertChar(char),
+    DeleteBackward,
+    DeleteForward,
+}
+
+/// Moves a view's viewport. There's no per-view caret to move independently
+/// of the scroll position, so horizontal and vertical movement both pan the
+/// view.
+#[derive(Debug, Clone, Copy)]
+pub enum MovementOp {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// The result of interpreting a single key press, still needing a
+/// view/document pair to actually apply against.
+#[derive(Debug, Clone, Copy)]
+pub enum Operation {
+    Document(DocumentOp),
+    Edit(EditOp),
+    Movement(MovementOp),
+    Register(RegisterOp),
+}
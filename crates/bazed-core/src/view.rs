@@ -0,0 +1,61 @@
+//! A view onto a document: the part of a [crate::document::Document] a
+//! single pane actually renders. Multiple views can point at the same
+//! document (e.g. after a window split), each scrolled to a different part
+//! of it.
+
+use uuid::Uuid;
+
+use crate::document::DocumentId;
+
+/// Identifies an open view for the lifetime of the core process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ViewId(Uuid);
+
+impl ViewId {
+    pub fn gen() -> Self {
+        ViewId(Uuid::new_v4())
+    }
+
+    pub fn from_uuid(id: Uuid) -> Self {
+        ViewId(id)
+    }
+}
+
+impl From<ViewId> for Uuid {
+    fn from(id: ViewId) -> Self {
+        id.0
+    }
+}
+
+impl std::fmt::Display for ViewId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// One pane's view onto a document: which lines of it are visible, and its
+/// own scroll state. Doesn't carry a caret; editing currently happens at the
+/// document's own buffer position rather than a per-view one.
+pub struct View {
+    pub document_id: DocumentId,
+    pub height: usize,
+    pub width: usize,
+    pub first_line: usize,
+    pub first_col: usize,
+    /// Accumulated fractional scroll, in lines, not yet folded into
+    /// `first_line`. See [crate::app::App::handle_mouse_scroll].
+    pub scroll_fraction: f64,
+}
+
+impl View {
+    pub fn new(document_id: DocumentId, height: usize, width: usize) -> Self {
+        View {
+            document_id,
+            height,
+            width,
+            first_line: 0,
+            first_col: 0,
+            scroll_fraction: 0.0,
+        }
+    }
+}
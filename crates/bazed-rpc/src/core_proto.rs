@@ -11,13 +11,101 @@ use crate::keycode::KeyInput;
 pub struct RequestId(pub Uuid);
 
 /// Absolute position within a document
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub struct CaretPosition {
     pub line: usize,
     pub col: usize,
 }
 
+/// A text edit expressed as a range to replace and the text to replace it
+/// with, the same shape an editor frontend already thinks in. The core
+/// translates these to and from CRDT operations internally.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct TextChange {
+    pub range: (CaretPosition, CaretPosition),
+    pub new_text: String,
+}
+
+/// How severe a [Diagnostic] is, mirroring the LSP `DiagnosticSeverity` scale.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticSeverity {
+    Error,
+    Warning,
+    Information,
+    Hint,
+}
+
+/// A single diagnostic (error, warning, ...) reported by a language server.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct Diagnostic {
+    pub range: (CaretPosition, CaretPosition),
+    pub severity: DiagnosticSeverity,
+    pub message: String,
+}
+
+/// What changed on a line of a document, relative to its content at HEAD.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffKind {
+    Added,
+    Modified,
+    Deleted,
+}
+
+/// Which way a [ToBackend::SplitView] divides the space its target view
+/// occupies.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SplitDirection {
+    Horizontal,
+    Vertical,
+}
+
+/// Which neighboring pane to move focus to with [ToBackend::MoveFocus].
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FocusDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// A window's layout, as a tree of horizontal/vertical splits whose leaves
+/// are views. Sent to the frontend so it can arrange panes.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum LayoutNode {
+    Leaf {
+        view_id: Uuid,
+    },
+    Split {
+        direction: SplitDirection,
+        children: Vec<LayoutNode>,
+    },
+}
+
+/// A single diff gutter marker for one line of a view.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DiffHunk {
+    pub line: usize,
+    pub kind: DiffKind,
+}
+
+/// A single entry in a completion list, as offered by a language server.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct CompletionItem {
+    pub label: String,
+    pub detail: Option<String>,
+    pub insert_text: String,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case", tag = "method", content = "params")]
 pub enum ToFrontend {
@@ -31,17 +119,78 @@ pub enum ToFrontend {
     UpdateView {
         view_id: Uuid,
         first_line: usize,
+        /// Fractional scroll past (or, if negative, before) `first_line`,
+        /// in `(-1.0, 1.0)`, so the frontend can render a smooth
+        /// partial-line shift instead of snapping line-by-line.
+        scroll_fraction: f64,
         height: usize,
         text: Vec<String>,
         /// caret positions are absolute
         carets: Vec<CaretPosition>,
         vim_mode: String,
+        /// Whether the document has edits since its last successful save.
+        modified: bool,
     },
     /// Response to the [ToBackend::ViewOpened] request
     ViewOpenedResponse {
         request_id: RequestId,
         view_id: Uuid,
     },
+    /// A remote site applied an edit to a document this view is showing.
+    /// Sent in response to [ToBackend::RemoteEdit] from any other client,
+    /// never as an echo of the sender's own edit.
+    ApplyEdit {
+        view_id: Uuid,
+        change: TextChange,
+    },
+    /// Diagnostics for a document, as last published by its language server.
+    /// Replaces any diagnostics previously published for the same document.
+    PublishDiagnostics {
+        document_id: Uuid,
+        diagnostics: Vec<Diagnostic>,
+    },
+    /// Response to the [ToBackend::RequestCompletion] request
+    CompletionResponse {
+        request_id: RequestId,
+        items: Vec<CompletionItem>,
+    },
+    /// The core wants to paste from the `"*"`/`"+"` register and needs the
+    /// frontend, which owns the windowing layer, to read the real system
+    /// clipboard. Answered by [ToBackend::ClipboardContent].
+    RequestClipboard {
+        request_id: RequestId,
+    },
+    /// The core yanked or deleted into the `"*"`/`"+"` register and wants the
+    /// frontend to write it to the real system clipboard.
+    SetClipboard {
+        text: String,
+    },
+    /// Diff gutter markers for a view, recomputed against HEAD after the
+    /// document's content changes. Replaces any hunks previously sent for
+    /// this view.
+    UpdateDiff {
+        view_id: Uuid,
+        hunks: Vec<DiffHunk>,
+    },
+    /// The window's layout changed, e.g. because of a split, a close, or a
+    /// focus change. Describes the whole tree, not a diff of it.
+    UpdateLayout {
+        tree: LayoutNode,
+        focused_view_id: Uuid,
+    },
+    /// A save job finished writing the buffer as of `revision` to disk. If
+    /// the document has been edited since, it's still modified relative to
+    /// the latest revision even though this save succeeded.
+    DocumentSaved {
+        document_id: Uuid,
+        revision: u64,
+    },
+    /// A save job failed; the document's on-disk content was left
+    /// unchanged.
+    SaveFailed {
+        document_id: Uuid,
+        error: String,
+    },
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -63,18 +212,59 @@ pub enum ToBackend {
     /// Mouse wheel turned notification.
     MouseScroll {
         view_id: Uuid,
-        /// Positive or negative values mean scrolling down or up respectively
-        line_delta: i32,
+        /// High-resolution scroll delta, in lines. Positive or negative
+        /// values mean scrolling down or up respectively. Devices that only
+        /// report whole lines (most mouse wheels) just send integral
+        /// values; trackpads and high-res wheels can send fractions of a
+        /// line, which the core accumulates until they cross a whole line.
+        delta_y: f64,
     },
     /// Send when the viewport for a given view has changed,
     /// i.e. because the window was resized or the user scrolled.
     ViewportChanged {
         view_id: Uuid,
         height: usize,
+        width: usize,
+        first_line: usize,
+        first_col: usize,
     },
     ViewOpened {
         request_id: RequestId,
         document_id: Uuid,
         height: usize,
+        width: usize,
+    },
+    /// An edit made by this client, to be merged via CRDT and broadcast to
+    /// every other view onto the same document.
+    RemoteEdit {
+        view_id: Uuid,
+        change: TextChange,
+    },
+    /// Request completions at a position, answered by
+    /// [ToFrontend::CompletionResponse].
+    RequestCompletion {
+        request_id: RequestId,
+        view_id: Uuid,
+        position: CaretPosition,
+    },
+    /// Answer to [ToFrontend::RequestClipboard] with the real system
+    /// clipboard's contents.
+    ClipboardContent {
+        request_id: RequestId,
+        text: String,
+    },
+    /// Split the given view's pane, opening a new view onto the same
+    /// document next to it.
+    SplitView {
+        view_id: Uuid,
+        direction: SplitDirection,
+    },
+    /// Close a view's pane. The last remaining pane cannot be closed.
+    CloseView {
+        view_id: Uuid,
+    },
+    /// Move focus to the pane neighboring the currently focused one.
+    MoveFocus {
+        direction: FocusDirection,
     },
 }
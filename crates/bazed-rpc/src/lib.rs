@@ -0,0 +1,3 @@
+pub mod core_proto;
+pub mod keycode;
+pub mod server;
@@ -0,0 +1,61 @@
+//! TCP transport for the core<->frontend RPC protocol. Messages are encoded
+//! as one JSON object per line, in whichever direction they travel.
+
+use color_eyre::{eyre::eyre, Result};
+use futures::{Stream, SinkExt, StreamExt};
+use tokio::{net::TcpListener, sync::mpsc};
+use tokio_util::codec::{FramedRead, FramedWrite, LinesCodec};
+use uuid::Uuid;
+
+use crate::core_proto::{ToBackend, ToFrontend};
+
+/// A cloneable handle for sending RPCs to the connected frontend. Cheap to
+/// clone and hand out to every task that needs to push an event, since it's
+/// just a channel sender backed by the actual write task.
+#[derive(Clone)]
+pub struct ClientSendHandle {
+    sender: mpsc::UnboundedSender<ToFrontend>,
+}
+
+impl ClientSendHandle {
+    pub async fn send_rpc(&self, message: ToFrontend) -> Result<()> {
+        self.sender
+            .send(message)
+            .map_err(|_| eyre!("client connection closed"))
+    }
+}
+
+/// Wait for a single frontend to connect to `addr`, returning a handle to
+/// send it RPCs, a stream of the RPCs it sends us, and a freshly-generated
+/// site id identifying this connection's edits in the core's CRDT buffers.
+/// The core only ever talks to one frontend at a time, so this resolves
+/// once and the listener is dropped afterwards.
+pub async fn wait_for_client(
+    addr: &str,
+) -> Result<(ClientSendHandle, impl Stream<Item = ToBackend> + Unpin, Uuid)> {
+    let listener = TcpListener::bind(addr).await?;
+    let (socket, _) = listener.accept().await?;
+    let site_id = Uuid::new_v4();
+    let (read_half, write_half) = socket.into_split();
+
+    let (sender, mut outgoing) = mpsc::unbounded_channel::<ToFrontend>();
+    tokio::spawn(async move {
+        let mut writer = FramedWrite::new(write_half, LinesCodec::new());
+        while let Some(message) = outgoing.recv().await {
+            let Ok(json) = serde_json::to_string(&message) else {
+                continue;
+            };
+            if writer.send(json).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let reader = FramedRead::new(read_half, LinesCodec::new());
+    let calls = reader.filter_map(|line| async move {
+        let line = line.ok()?;
+        serde_json::from_str(&line).ok()
+    });
+
+    Ok((ClientSendHandle { sender }, Box::pin(calls), site_id))
+}